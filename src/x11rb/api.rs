@@ -0,0 +1,610 @@
+/*!
+ *  The x11rb equivalent of [xcb::Api][crate::xcb::Api]: a thin, penrose-shaped
+ *  wrapper around a [RustConnection][x11rb::rust_connection::RustConnection]
+ *  that [X11rbConnection][super::xconn::X11rbConnection] drives to implement
+ *  [XConn][crate::core::xconnection::XConn].
+ *
+ *  Everything below translates between raw x11rb request/reply types and
+ *  penrose's neutral [XEvent], [Atom], [PropVal], [WinConfig] and [WinAttr]
+ *  vocabulary so that nothing above this module needs to know which X11
+ *  library is actually doing the talking.
+ */
+use crate::{
+    core::{
+        bindings::{KeyCode, MouseState},
+        data_types::{Point, PropVal, Region, WinAttr, WinConfig, WinId, WinType},
+        screen::Screen,
+        xconnection::{Atom, XEvent},
+    },
+    Error, Result,
+};
+
+use std::{cell::RefCell, collections::HashMap};
+
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        randr::ConnectionExt as _,
+        xproto::{
+            AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent,
+            ConfigureWindowAux, ConnectionExt as _, EventMask, GrabMode, InputFocus, PropMode,
+            StackMode, Window,
+        },
+        Event,
+    },
+    rust_connection::RustConnection,
+    CURRENT_TIME,
+};
+
+/// A connection to the X server via x11rb, along with the root window and a
+/// lazily populated cache of the (non-predefined) X atoms penrose interns and
+/// refers to by name. Mirrors [xcb::Api][crate::xcb::Api].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Api {
+    #[cfg_attr(feature = "serde", serde(skip))]
+    conn: RustConnection,
+    screen_num: usize,
+    root: WinId,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    atoms: RefCell<HashMap<Atom, u32>>,
+}
+
+/// The penrose-facing surface of the x11rb backend: property read/write,
+/// window config, RandR screen query, event wait, and key/button grabbing.
+/// Implemented by [Api]; kept as a trait (mirroring
+/// [XcbApi][crate::xcb::XcbApi]) so it can be brought into scope alongside
+/// [Api] itself without re-exporting every inherent method.
+pub trait X11rbApi {
+    fn known_atom(&self, atom: Atom) -> u32;
+    fn known_atoms(&self) -> HashMap<Atom, u32>;
+    fn atom(&self, name: &str) -> Result<u32>;
+    fn root(&self) -> WinId;
+    fn conn(&self) -> &RustConnection;
+
+    fn get_atom_prop(&self, id: WinId, atom: Atom) -> Result<u32>;
+    fn get_atom_list_prop(&self, id: WinId, atom: Atom) -> Result<Vec<u32>>;
+    fn get_cardinal_list_prop(&self, id: WinId, atom: Atom) -> Result<Vec<u32>>;
+    fn get_window_prop(&self, id: WinId, atom: Atom) -> Result<WinId>;
+    fn get_str_prop(&self, id: WinId, name: &str) -> Result<String>;
+    fn get_wm_hints_input(&self, id: WinId) -> Result<bool>;
+
+    fn replace_prop(&self, id: WinId, atom: Atom, val: PropVal<'_>);
+    fn delete_prop(&self, id: WinId, atom: Atom);
+    fn change_property(&self, id: WinId, name: Atom, type_: u32, format: u8, data: &[u32]);
+
+    fn create_window(&self, ty: WinType, reg: Region, managed: bool) -> Result<WinId>;
+    fn destroy_window(&self, id: WinId);
+    fn map_window(&self, id: WinId);
+    fn unmap_window(&self, id: WinId);
+    fn configure_window(&self, id: WinId, data: &[WinConfig]);
+    fn set_window_attributes(&self, id: WinId, data: &[WinAttr]);
+    fn window_geometry(&self, id: WinId) -> Result<Region>;
+
+    fn current_screens(&self) -> Result<Vec<Screen>>;
+    fn current_clients(&self) -> Result<Vec<WinId>>;
+    fn set_randr_notify_mask(&self) -> Result<()>;
+
+    fn cursor_position(&self) -> Point;
+    fn warp_cursor(&self, id: WinId, x: usize, y: usize);
+
+    fn focused_client(&self) -> Result<WinId>;
+    fn mark_focused_window(&self, id: WinId);
+    fn kill_client(&self, id: WinId);
+
+    fn send_client_event(&self, id: WinId, atom_name: &str) -> Result<()>;
+    fn send_client_event_data(&self, id: WinId, atom: Atom, data: &[u32; 5]);
+
+    fn grab_keys(&self, keys: &[KeyCode]);
+    fn grab_mouse_buttons(&self, buttons: &[MouseState]);
+    fn ungrab_keys(&self);
+    fn ungrab_mouse_buttons(&self);
+
+    fn flush(&self) -> bool;
+    fn wait_for_event(&self) -> Result<XEvent>;
+
+    #[cfg(feature = "serde")]
+    fn hydrate(&mut self) -> Result<()>;
+}
+
+impl Api {
+    /// Establish a new connection to the running X server. Fails if unable to connect
+    pub fn new() -> Result<Self> {
+        let (conn, screen_num) = RustConnection::connect(None)
+            .map_err(|e| Error::Raw(format!("unable to connect to the X server: {}", e)))?;
+        let root = conn.setup().roots[screen_num].root;
+
+        Ok(Self {
+            conn,
+            screen_num,
+            root,
+            atoms: RefCell::new(HashMap::new()),
+        })
+    }
+
+    fn intern(&self, name: &str) -> Result<u32> {
+        Ok(self
+            .conn
+            .intern_atom(false, name.as_bytes())
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .reply()
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .atom)
+    }
+}
+
+impl X11rbApi for Api {
+    fn known_atom(&self, atom: Atom) -> u32 {
+        if let Some(a) = self.atoms.borrow().get(&atom) {
+            return *a;
+        }
+
+        let interned = self.intern(atom.as_ref()).unwrap_or(0);
+        self.atoms.borrow_mut().insert(atom, interned);
+        interned
+    }
+
+    fn known_atoms(&self) -> HashMap<Atom, u32> {
+        // A snapshot, not a reference: `known_atom` lazily inserts into this
+        // same RefCell, so handing out a borrow here would alias a live
+        // `borrow_mut()` the moment a caller interned something new.
+        self.atoms.borrow().clone()
+    }
+
+    fn atom(&self, name: &str) -> Result<u32> {
+        self.intern(name)
+    }
+
+    fn root(&self) -> WinId {
+        self.root
+    }
+
+    fn conn(&self) -> &RustConnection {
+        &self.conn
+    }
+
+    fn get_atom_prop(&self, id: WinId, atom: Atom) -> Result<u32> {
+        let vals = self.get_cardinal_list_prop(id, atom)?;
+        vals.first()
+            .copied()
+            .ok_or_else(|| Error::Raw(format!("{:?} not set on {}", atom, id)))
+    }
+
+    fn get_atom_list_prop(&self, id: WinId, atom: Atom) -> Result<Vec<u32>> {
+        let name = self.known_atom(atom);
+        let reply = self
+            .conn
+            .get_property(false, id as Window, name, AtomEnum::ATOM, 0, u32::MAX)
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .reply()
+            .map_err(|e| Error::Raw(e.to_string()))?;
+
+        Ok(reply.value32().map(|v| v.collect()).unwrap_or_default())
+    }
+
+    fn get_cardinal_list_prop(&self, id: WinId, atom: Atom) -> Result<Vec<u32>> {
+        // Any type is accepted: WM_STATE, WM_HINTS and WM_NORMAL_HINTS are
+        // format-32 lists whose property type is not CARDINAL.
+        let name = self.known_atom(atom);
+        let reply = self
+            .conn
+            .get_property(false, id as Window, name, AtomEnum::ANY, 0, u32::MAX)
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .reply()
+            .map_err(|e| Error::Raw(e.to_string()))?;
+
+        Ok(reply.value32().map(|v| v.collect()).unwrap_or_default())
+    }
+
+    fn get_window_prop(&self, id: WinId, atom: Atom) -> Result<WinId> {
+        let name = self.known_atom(atom);
+        let reply = self
+            .conn
+            .get_property(false, id as Window, name, AtomEnum::WINDOW, 0, 1)
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .reply()
+            .map_err(|e| Error::Raw(e.to_string()))?;
+
+        reply
+            .value32()
+            .and_then(|mut v| v.next())
+            .map(|w| w as WinId)
+            .ok_or_else(|| Error::Raw(format!("{:?} not set on {}", atom, id)))
+    }
+
+    fn get_str_prop(&self, id: WinId, name: &str) -> Result<String> {
+        let atom = self.intern(name)?;
+        let reply = self
+            .conn
+            .get_property(false, id as Window, atom, AtomEnum::ANY, 0, u32::MAX)
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .reply()
+            .map_err(|e| Error::Raw(e.to_string()))?;
+
+        String::from_utf8(reply.value).map_err(|e| Error::Raw(e.to_string()))
+    }
+
+    fn get_wm_hints_input(&self, id: WinId) -> Result<bool> {
+        const INPUT_HINT_FLAG: u32 = 1 << 0;
+
+        let vals = self.get_cardinal_list_prop(id, Atom::WmHints)?;
+        match (vals.first(), vals.get(1)) {
+            (Some(flags), Some(input)) if flags & INPUT_HINT_FLAG != 0 => Ok(*input != 0),
+            _ => Ok(true),
+        }
+    }
+
+    fn replace_prop(&self, id: WinId, atom: Atom, val: PropVal<'_>) {
+        let name = self.known_atom(atom);
+        let (type_, format, data): (u32, u8, Vec<u8>) = match val {
+            PropVal::Atom(vals) => (
+                AtomEnum::ATOM.into(),
+                32,
+                vals.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+            ),
+            PropVal::Cardinal(vals) => (
+                AtomEnum::CARDINAL.into(),
+                32,
+                vals.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+            ),
+            PropVal::Window(vals) => (
+                AtomEnum::WINDOW.into(),
+                32,
+                vals.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+            ),
+            PropVal::Str(s) => (AtomEnum::STRING.into(), 8, s.as_bytes().to_vec()),
+        };
+
+        let _ = self.conn.change_property(
+            PropMode::REPLACE,
+            id as Window,
+            name,
+            type_,
+            format,
+            (data.len() as u32) / (format as u32 / 8).max(1),
+            &data,
+        );
+    }
+
+    fn delete_prop(&self, id: WinId, atom: Atom) {
+        let name = self.known_atom(atom);
+        let _ = self.conn.delete_property(id as Window, name);
+    }
+
+    fn change_property(&self, id: WinId, name: Atom, type_: u32, format: u8, data: &[u32]) {
+        let name = self.known_atom(name);
+        let bytes: Vec<u8> = data.iter().flat_map(|v| v.to_ne_bytes()).collect();
+        let _ = self.conn.change_property(
+            PropMode::REPLACE,
+            id as Window,
+            name,
+            type_,
+            format,
+            data.len() as u32,
+            &bytes,
+        );
+    }
+
+    fn create_window(&self, _ty: WinType, reg: Region, managed: bool) -> Result<WinId> {
+        let (x, y, w, h) = reg.values();
+        let id = self.conn.generate_id().map_err(|e| Error::Raw(e.to_string()))?;
+        let aux = ChangeWindowAttributesAux::new().override_redirect(if managed { 0 } else { 1 });
+
+        self.conn
+            .create_window(
+                x11rb::COPY_DEPTH_FROM_PARENT,
+                id,
+                self.root as Window,
+                x as i16,
+                y as i16,
+                w as u16,
+                h as u16,
+                0,
+                x11rb::protocol::xproto::WindowClass::INPUT_OUTPUT,
+                0,
+                &aux,
+            )
+            .map_err(|e| Error::Raw(e.to_string()))?;
+
+        Ok(id as WinId)
+    }
+
+    fn destroy_window(&self, id: WinId) {
+        let _ = self.conn.destroy_window(id as Window);
+    }
+
+    fn map_window(&self, id: WinId) {
+        let _ = self.conn.map_window(id as Window);
+    }
+
+    fn unmap_window(&self, id: WinId) {
+        let _ = self.conn.unmap_window(id as Window);
+    }
+
+    fn configure_window(&self, id: WinId, data: &[WinConfig]) {
+        let mut aux = ConfigureWindowAux::new();
+        for cfg in data {
+            aux = match *cfg {
+                WinConfig::Position(region) => {
+                    let (x, y, w, h) = region.values();
+                    aux.x(x as i32).y(y as i32).width(w).height(h)
+                }
+                WinConfig::BorderPx(px) => aux.border_width(px),
+                WinConfig::StackAbove => aux.stack_mode(StackMode::ABOVE),
+            };
+        }
+
+        let _ = self.conn.configure_window(id as Window, &aux);
+    }
+
+    fn set_window_attributes(&self, id: WinId, data: &[WinAttr]) {
+        let mut aux = ChangeWindowAttributesAux::new();
+        for attr in data {
+            aux = match *attr {
+                WinAttr::BorderColor(c) => aux.border_pixel(c),
+                WinAttr::ClientEventMask => aux.event_mask(
+                    EventMask::ENTER_WINDOW
+                        | EventMask::FOCUS_CHANGE
+                        | EventMask::PROPERTY_CHANGE
+                        | EventMask::STRUCTURE_NOTIFY,
+                ),
+                WinAttr::RootEventMask => aux.event_mask(
+                    EventMask::SUBSTRUCTURE_REDIRECT
+                        | EventMask::SUBSTRUCTURE_NOTIFY
+                        | EventMask::BUTTON_PRESS
+                        | EventMask::POINTER_MOTION
+                        | EventMask::STRUCTURE_NOTIFY,
+                ),
+            };
+        }
+
+        let _ = self.conn.change_window_attributes(id as Window, &aux);
+    }
+
+    fn window_geometry(&self, id: WinId) -> Result<Region> {
+        let g = self
+            .conn
+            .get_geometry(id as Window)
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .reply()
+            .map_err(|e| Error::Raw(e.to_string()))?;
+
+        Ok(Region::new(
+            g.x as u32,
+            g.y as u32,
+            g.width as u32,
+            g.height as u32,
+        ))
+    }
+
+    fn current_screens(&self) -> Result<Vec<Screen>> {
+        let resources = self
+            .conn
+            .randr_get_screen_resources_current(self.root as Window)
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .reply()
+            .map_err(|e| Error::Raw(e.to_string()))?;
+
+        let mut screens = Vec::new();
+        for crtc in &resources.crtcs {
+            let info = self
+                .conn
+                .randr_get_crtc_info(*crtc, resources.config_timestamp)
+                .map_err(|e| Error::Raw(e.to_string()))?
+                .reply()
+                .map_err(|e| Error::Raw(e.to_string()))?;
+
+            if info.width == 0 || info.height == 0 {
+                continue;
+            }
+
+            let i = screens.len();
+            let region = Region::new(
+                info.x as u32,
+                info.y as u32,
+                info.width as u32,
+                info.height as u32,
+            );
+            screens.push(Screen::new(region, i));
+        }
+
+        Ok(screens)
+    }
+
+    fn current_clients(&self) -> Result<Vec<WinId>> {
+        let tree = self
+            .conn
+            .query_tree(self.root as Window)
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .reply()
+            .map_err(|e| Error::Raw(e.to_string()))?;
+
+        Ok(tree.children.iter().map(|&w| w as WinId).collect())
+    }
+
+    fn set_randr_notify_mask(&self) -> Result<()> {
+        self.conn
+            .randr_select_input(
+                self.root as Window,
+                x11rb::protocol::randr::NotifyMask::SCREEN_CHANGE,
+            )
+            .map_err(|e| Error::Raw(e.to_string()))?;
+        Ok(())
+    }
+
+    fn cursor_position(&self) -> Point {
+        match self
+            .conn
+            .query_pointer(self.root as Window)
+            .ok()
+            .and_then(|c| c.reply().ok())
+        {
+            Some(reply) => Point::new(reply.root_x as u32, reply.root_y as u32),
+            None => Point::new(0, 0),
+        }
+    }
+
+    fn warp_cursor(&self, id: WinId, x: usize, y: usize) {
+        let _ = self.conn.warp_pointer(
+            x11rb::NONE,
+            id as Window,
+            0,
+            0,
+            0,
+            0,
+            x as i16,
+            y as i16,
+        );
+    }
+
+    fn focused_client(&self) -> Result<WinId> {
+        Ok(self
+            .conn
+            .get_input_focus()
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .reply()
+            .map_err(|e| Error::Raw(e.to_string()))?
+            .focus as WinId)
+    }
+
+    fn mark_focused_window(&self, id: WinId) {
+        let _ = self
+            .conn
+            .set_input_focus(InputFocus::PARENT, id as Window, CURRENT_TIME);
+    }
+
+    fn kill_client(&self, id: WinId) {
+        let _ = self.conn.kill_client(id as Window);
+    }
+
+    fn send_client_event(&self, id: WinId, atom_name: &str) -> Result<()> {
+        let atom = self.intern(atom_name)?;
+        let data = [atom, CURRENT_TIME, 0, 0, 0];
+        let protocols_atom = self.intern("WM_PROTOCOLS")?;
+        self.send_raw_client_message(id, protocols_atom, data);
+        Ok(())
+    }
+
+    fn send_client_event_data(&self, id: WinId, atom: Atom, data: &[u32; 5]) {
+        let name = self.known_atom(atom);
+        self.send_raw_client_message(id, name, *data);
+    }
+
+    fn grab_keys(&self, keys: &[KeyCode]) {
+        for k in keys {
+            let _ = self.conn.grab_key(
+                true,
+                self.root as Window,
+                k.mask(),
+                k.code(),
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            );
+        }
+    }
+
+    fn grab_mouse_buttons(&self, buttons: &[MouseState]) {
+        for b in buttons {
+            let _ = self.conn.grab_button(
+                true,
+                self.root as Window,
+                (EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE).into(),
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                x11rb::NONE,
+                b.button(),
+                b.mask(),
+            );
+        }
+    }
+
+    fn ungrab_keys(&self) {
+        let _ = self
+            .conn
+            .ungrab_key(x11rb::protocol::xproto::Grab::ANY as u8, self.root as Window, x11rb::protocol::xproto::ModMask::ANY);
+    }
+
+    fn ungrab_mouse_buttons(&self) {
+        let _ = self.conn.ungrab_button(
+            x11rb::protocol::xproto::ButtonIndex::ANY,
+            self.root as Window,
+            x11rb::protocol::xproto::ModMask::ANY,
+        );
+    }
+
+    fn flush(&self) -> bool {
+        self.conn.flush().is_ok()
+    }
+
+    fn wait_for_event(&self) -> Result<XEvent> {
+        loop {
+            let event = self
+                .conn
+                .wait_for_event()
+                .map_err(|e| Error::Raw(e.to_string()))?;
+
+            if let Some(xev) = self.to_xevent(event) {
+                return Ok(xev);
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn hydrate(&mut self) -> Result<()> {
+        let (conn, screen_num) = RustConnection::connect(None)
+            .map_err(|e| Error::Raw(format!("unable to reconnect to the X server: {}", e)))?;
+        self.root = conn.setup().roots[screen_num].root;
+        self.conn = conn;
+        self.screen_num = screen_num;
+        Ok(())
+    }
+}
+
+impl Api {
+    fn send_raw_client_message(&self, id: WinId, message_type: u32, data: [u32; 5]) {
+        let event = ClientMessageEvent::new(32, id as Window, message_type, data);
+        let _ = self.conn.send_event(
+            false,
+            id as Window,
+            EventMask::NO_EVENT,
+            event,
+        );
+    }
+
+    /// Translate a raw x11rb [Event] into penrose's neutral [XEvent],
+    /// dropping anything we don't currently act on.
+    fn to_xevent(&self, event: Event) -> Option<XEvent> {
+        match event {
+            Event::MapRequest(e) => Some(XEvent::MapRequest(e.window as WinId)),
+            Event::UnmapNotify(e) => Some(XEvent::UnmapNotify(e.window as WinId)),
+            Event::DestroyNotify(e) => Some(XEvent::Destroy(e.window as WinId)),
+            Event::ConfigureNotify(e) => Some(XEvent::ScreenChange)
+                .filter(|_| e.window == self.root as Window),
+            Event::PropertyNotify(e) => Some(XEvent::PropertyNotify {
+                id: e.window as WinId,
+                atom: e.atom,
+                is_root: e.window == self.root as Window,
+            }),
+            Event::ClientMessage(e) => {
+                let data = e.data.as_data32();
+                Some(XEvent::ClientMessage {
+                    id: e.window as WinId,
+                    dtype: e.type_,
+                    data: data.to_vec(),
+                })
+            }
+            Event::KeyPress(e) => Some(XEvent::KeyPress(e.detail, e.state.into())),
+            Event::ButtonPress(e) => Some(XEvent::MouseEvent {
+                id: e.event as WinId,
+                x: e.event_x as i16,
+                y: e.event_y as i16,
+                button: e.detail,
+                state: e.state.into(),
+            }),
+            Event::EnterNotify(e) => Some(XEvent::Enter(e.event as WinId)),
+            Event::RandrScreenChangeNotify(_) => Some(XEvent::ScreenChange),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! A pure-Rust alternative to the [xcb][crate::xcb] backend, built on top of
+//! the [x11rb](https://docs.rs/x11rb) crate instead of the autogenerated C
+//! `rust-xcb` bindings. Gated behind the `x11rb` feature flag so that
+//! building without a C toolchain / the system XCB headers is possible.
+//!
+//! `WindowManager<x11rb::xconn::X11rbConnection>` is a drop-in replacement
+//! for `WindowManager<xcb::xconn::XcbConnection>`: both implement the same
+//! [XConn][crate::core::xconnection::XConn] trait against the same neutral
+//! [XEvent][crate::core::xconnection::XEvent], [Atom][crate::core::xconnection::Atom],
+//! [PropVal][crate::core::data_types::PropVal], [WinConfig][crate::core::data_types::WinConfig]
+//! and [WinAttr][crate::core::data_types::WinAttr] vocabulary.
+#[cfg(feature = "x11rb")]
+pub mod api;
+#[cfg(feature = "x11rb")]
+pub mod xconn;
+
+#[cfg(feature = "x11rb")]
+pub use api::{Api, X11rbApi};
+#[cfg(feature = "x11rb")]
+pub use xconn::X11rbConnection;
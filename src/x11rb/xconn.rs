@@ -0,0 +1,539 @@
+/*!
+ *  API wrapper for talking to the X server using x11rb
+ *
+ *  This is an alternative to the [xcb][crate::xcb] backend that talks to the X
+ *  server through the pure-Rust [x11rb](https://docs.rs/x11rb) crate instead of
+ *  the autogenerated C `rust-xcb` bindings. It exists so that penrose can be
+ *  built (and statically linked / cross-compiled) without a C toolchain or the
+ *  system XCB headers. It is feature gated behind `x11rb` and implements the
+ *  same [XConn] trait as [XcbConnection][crate::xcb::xconn::XcbConnection], so
+ *  `WindowManager<X11rbConnection>` is a drop-in replacement for
+ *  `WindowManager<crate::xcb::xconn::XcbConnection>`.
+ *
+ *  [EWMH](https://specifications.freedesktop.org/wm-spec/wm-spec-1.3.html)
+ *  [ICCCM](https://tronche.com/gui/x/icccm/)
+ */
+use crate::{
+    core::{
+        bindings::{KeyBindings, MouseBindings},
+        data_types::{Point, PropVal, Region, WinAttr, WinConfig, WinId, WinType},
+        manager::WindowManager,
+        screen::Screen,
+        xconnection::{
+            Atom, XConn, XEvent, AUTO_FLOAT_WINDOW_TYPES, EWMH_SUPPORTED_ATOMS,
+            UNMANAGED_WINDOW_TYPES,
+        },
+    },
+    x11rb::{Api, X11rbApi},
+    xconn_shared::{
+        self, accepts_input_focus, center_transient_region, clamp_to_size_hints,
+        parse_size_hints, set_window_state, supports_protocol, window_state, HintsApi, SizeHints,
+        Strut, WmState,
+    },
+    Result,
+};
+
+use std::{cell::RefCell, collections::HashMap, str::FromStr};
+
+const WM_NAME: &str = "penrose";
+
+impl HintsApi for Api {
+    fn known_atom(&self, atom: Atom) -> u32 {
+        X11rbApi::known_atom(self, atom)
+    }
+
+    fn get_atom_list_prop(&self, id: WinId, atom: Atom) -> Result<Vec<u32>> {
+        Ok(X11rbApi::get_atom_list_prop(self, id, atom)?)
+    }
+
+    fn get_cardinal_list_prop(&self, id: WinId, atom: Atom) -> Result<Vec<u32>> {
+        Ok(X11rbApi::get_cardinal_list_prop(self, id, atom)?)
+    }
+
+    fn get_wm_hints_input(&self, id: WinId) -> Result<bool> {
+        Ok(X11rbApi::get_wm_hints_input(self, id)?)
+    }
+
+    fn get_window_prop(&self, id: WinId, atom: Atom) -> Result<WinId> {
+        Ok(X11rbApi::get_window_prop(self, id, atom)?)
+    }
+
+    fn root(&self) -> WinId {
+        X11rbApi::root(self)
+    }
+
+    fn current_screens(&self) -> Result<Vec<Screen>> {
+        Ok(X11rbApi::current_screens(self)?)
+    }
+
+    fn window_geometry(&self, id: WinId) -> Result<Region> {
+        Ok(X11rbApi::window_geometry(self, id)?)
+    }
+
+    fn replace_cardinal_prop(&self, id: WinId, atom: Atom, data: &[u32]) {
+        X11rbApi::replace_prop(self, id, atom, PropVal::Cardinal(data))
+    }
+
+    fn change_property(&self, id: WinId, name: Atom, type_: u32, format: u8, data: &[u32]) {
+        X11rbApi::change_property(self, id, name, type_, format, data)
+    }
+}
+
+/**
+ * Handles communication with an X server via x11rb.
+ *
+ * X11rbConnection mirrors [XcbConnection][crate::xcb::xconn::XcbConnection]'s
+ * surface so that it can be swapped in without touching any code above the
+ * [XConn] trait boundary.
+ **/
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct X11rbConnection {
+    api: Api,
+    check_win: WinId,
+    auto_float_types: Vec<u32>,
+    dont_manage_types: Vec<u32>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    struts: RefCell<HashMap<WinId, Strut>>,
+}
+
+impl X11rbConnection {
+    /// Establish a new connection to the running X server. Fails if unable to connect
+    pub fn new() -> Result<Self> {
+        let api = Api::new()?;
+        let auto_float_types: Vec<u32> = AUTO_FLOAT_WINDOW_TYPES
+            .iter()
+            .map(|a| api.known_atom(*a))
+            .collect();
+        let dont_manage_types: Vec<u32> = UNMANAGED_WINDOW_TYPES
+            .iter()
+            .map(|a| api.known_atom(*a))
+            .collect();
+
+        api.known_atom(Atom::WmProtocols);
+        api.known_atom(Atom::WmDeleteWindow);
+        api.known_atom(Atom::WmTakeFocus);
+
+        api.set_randr_notify_mask()?;
+        let check_win = api.create_window(WinType::CheckWin, Region::new(0, 0, 1, 1), false)?;
+
+        Ok(Self {
+            api,
+            check_win,
+            auto_float_types,
+            dont_manage_types,
+            struts: RefCell::new(HashMap::new()),
+        })
+    }
+
+    fn window_has_type_in(&self, id: WinId, win_types: &[u32]) -> bool {
+        if let Ok(atom) = self.api.get_atom_prop(id, Atom::NetWmWindowType) {
+            return win_types.contains(&atom);
+        }
+        false
+    }
+
+    /// Re-read `id`'s strut and update the cache used by [current_outputs][XConn::current_outputs]
+    /// to compute each screen's effective working area. Call whenever a client
+    /// with a strut maps or has its strut property changed; [unmap_window][XConn::unmap_window]
+    /// drops the unmapped client's strut itself.
+    pub fn update_strut(&self, id: WinId) {
+        match xconn_shared::read_strut(&self.api, id) {
+            Some(strut) => {
+                self.struts.borrow_mut().insert(id, strut);
+            }
+            None => {
+                self.struts.borrow_mut().remove(&id);
+            }
+        }
+    }
+
+    /// Get a handle on the underlying [x11rb Connection][::x11rb::rust_connection::RustConnection]
+    /// used by [Api] to communicate with the X server.
+    pub fn x11rb_connection(&self) -> &x11rb::rust_connection::RustConnection {
+        self.api.conn()
+    }
+
+    /// Get a handle on the underlying [Api] to communicate with the X server.
+    pub fn api(&self) -> &Api {
+        &self.api
+    }
+
+    /// Get a mutable handle on the underlying [Api] to communicate with the X server.
+    pub fn api_mut(&mut self) -> &mut Api {
+        &mut self.api
+    }
+
+    /// The current interned [Atom] values known to the underlying [Api] connection
+    pub fn known_atoms(&self) -> HashMap<Atom, u32> {
+        self.api.known_atoms()
+    }
+}
+
+impl WindowManager<X11rbConnection> {
+    /// Get a handle on the underlying x11rb Connection used by [Api] to communicate with the X
+    /// server.
+    pub fn x11rb_connection(&self) -> &x11rb::rust_connection::RustConnection {
+        self.conn().x11rb_connection()
+    }
+
+    /// The current interned [Atom] values known to the underlying [X11rbConnection]
+    pub fn known_atoms(&self) -> HashMap<Atom, u32> {
+        self.conn().known_atoms()
+    }
+}
+
+impl XConn for X11rbConnection {
+    #[cfg(feature = "serde")]
+    fn hydrate(&mut self) -> Result<()> {
+        Ok(self.api.hydrate()?)
+    }
+
+    fn flush(&self) -> bool {
+        self.api.flush()
+    }
+
+    fn wait_for_event(&self) -> Result<XEvent> {
+        Ok(self.api.wait_for_event()?)
+    }
+
+    fn current_outputs(&self) -> Vec<Screen> {
+        match self.api.current_screens() {
+            Ok(mut screens) => {
+                let root = xconn_shared::root_bounds(&screens);
+                for screen in screens.iter_mut() {
+                    let effective = xconn_shared::apply_struts(
+                        screen.region(false),
+                        root,
+                        &self.struts.borrow(),
+                    );
+                    screen.set_effective_region(effective);
+                }
+                screens
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn cursor_position(&self) -> Point {
+        self.api.cursor_position()
+    }
+
+    fn position_window(
+        &self,
+        id: WinId,
+        reg: Region,
+        border: u32,
+        stack_above: bool,
+        floating: bool,
+    ) {
+        let reg = if floating {
+            let reg = match self.size_hints_for(id) {
+                Some(hints) => {
+                    let (x, y, w, h) = reg.values();
+                    let (w, h) = clamp_to_size_hints(w, h, &hints);
+                    Region::new(x, y, w, h)
+                }
+                None => reg,
+            };
+
+            // A transient (window_should_float floats it automatically) is
+            // still only auto-floated, not auto-placed: center it over its
+            // WM_TRANSIENT_FOR parent (or the screen under it) the same way a
+            // dialog box would be positioned by hand.
+            if self.transient_for(id).is_some() {
+                let (x, y, _, _) = reg.values();
+                match self
+                    .api
+                    .current_screens()
+                    .ok()
+                    .as_deref()
+                    .and_then(|screens| xconn_shared::screen_at(screens, x, y))
+                {
+                    Some(screen) => self.center_transient_region(id, screen),
+                    None => reg,
+                }
+            } else {
+                reg
+            }
+        } else {
+            reg
+        };
+
+        let mut data = vec![WinConfig::Position(reg), WinConfig::BorderPx(border)];
+        if stack_above {
+            data.push(WinConfig::StackAbove);
+        }
+        self.api.configure_window(id, &data)
+    }
+
+    /// Read and decode `id`'s ICCCM `WM_NORMAL_HINTS` property, if it has one.
+    fn size_hints_for(&self, id: WinId) -> Option<SizeHints> {
+        self.api
+            .get_cardinal_list_prop(id, Atom::WmNormalHints)
+            .ok()
+            .and_then(|vals| parse_size_hints(&vals))
+    }
+
+    fn raise_window(&self, id: WinId) {
+        self.api.configure_window(id, &[WinConfig::StackAbove])
+    }
+
+    fn mark_new_window(&self, id: WinId) {
+        let data = &[WinAttr::ClientEventMask];
+        self.api.set_window_attributes(id, data)
+    }
+
+    fn map_window(&self, id: WinId) {
+        self.api.map_window(id);
+        self.update_strut(id);
+        self.set_window_state(id, WmState::Normal);
+    }
+
+    fn unmap_window(&self, id: WinId) {
+        self.api.unmap_window(id);
+        self.struts.borrow_mut().remove(&id);
+        self.set_window_state(id, WmState::Iconic);
+    }
+
+    /// Write `id`'s ICCCM `WM_STATE` property.
+    fn set_window_state(&self, id: WinId, state: WmState) {
+        set_window_state(&self.api, id, state);
+    }
+
+    fn send_client_event(&self, id: WinId, atom_name: &str) -> Result<()> {
+        Ok(self.api.send_client_event(id, atom_name)?)
+    }
+
+    fn focused_client(&self) -> WinId {
+        self.api.focused_client().unwrap_or(0)
+    }
+
+    /// Focus `id`, additionally sending it a `WM_TAKE_FOCUS` ClientMessage
+    /// (carrying the timestamp of the event that triggered this focus change,
+    /// as ICCCM 4.2.7 forbids `CurrentTime` here) if it wants to manage its
+    /// own input focus.
+    fn focus_client(&self, id: WinId, timestamp: u32) {
+        self.api.mark_focused_window(id);
+        if supports_protocol(&self.api, id, Atom::WmTakeFocus)
+            && !accepts_input_focus(&self.api, id)
+        {
+            let data = [self.api.known_atom(Atom::WmTakeFocus), timestamp, 0, 0, 0];
+            self.api
+                .send_client_event_data(id, Atom::WmProtocols, &data);
+        }
+    }
+
+    /// Close `id` via the ICCCM `WM_DELETE_WINDOW` protocol if it is supported,
+    /// otherwise fall back to forcibly killing the client connection.
+    fn kill_client(&self, id: WinId) {
+        if supports_protocol(&self.api, id, Atom::WmDeleteWindow) {
+            let data = [
+                self.api.known_atom(Atom::WmDeleteWindow),
+                x11rb::CURRENT_TIME,
+                0,
+                0,
+                0,
+            ];
+            self.api
+                .send_client_event_data(id, Atom::WmProtocols, &data);
+        } else {
+            self.api.kill_client(id);
+        }
+    }
+
+    fn set_client_border_color(&self, id: WinId, color: u32) {
+        let data = &[WinAttr::BorderColor(color)];
+        self.api.set_window_attributes(id, data);
+    }
+
+    fn toggle_client_fullscreen(&self, id: WinId, client_is_fullscreen: bool) {
+        let data = if client_is_fullscreen {
+            0
+        } else {
+            self.api.known_atom(Atom::NetWmStateFullscreen)
+        };
+
+        self.api
+            .replace_prop(id, Atom::NetWmState, PropVal::Atom(&[data]));
+    }
+
+    fn grab_keys(&self, key_bindings: &KeyBindings<Self>, mouse_bindings: &MouseBindings<Self>) {
+        self.api.grab_keys(&key_bindings.keys().collect::<Vec<_>>());
+        self.api.grab_mouse_buttons(
+            &mouse_bindings
+                .keys()
+                .map(|(_, state)| state)
+                .collect::<Vec<_>>(),
+        );
+        let data = &[WinAttr::RootEventMask];
+        self.api.set_window_attributes(self.api.root(), data);
+        self.flush();
+    }
+
+    fn set_wm_properties(&self, workspaces: &[&str]) {
+        let root = self.api.root();
+        for &win in &[self.check_win, root] {
+            self.api.replace_prop(
+                win,
+                Atom::NetSupportingWmCheck,
+                PropVal::Window(&[self.check_win]),
+            );
+            let val = PropVal::Str(WM_NAME);
+            self.api.replace_prop(win, Atom::WmName, val);
+        }
+
+        let supported = EWMH_SUPPORTED_ATOMS
+            .iter()
+            .map(|a| self.api.known_atom(*a))
+            .collect::<Vec<u32>>();
+        let prop = PropVal::Atom(&supported);
+
+        self.api.replace_prop(root, Atom::NetSupported, prop);
+        self.update_desktops(workspaces);
+        self.api.delete_prop(root, Atom::NetClientList);
+    }
+
+    fn update_desktops(&self, workspaces: &[&str]) {
+        let root = self.api.root();
+        self.api.replace_prop(
+            root,
+            Atom::NetNumberOfDesktops,
+            PropVal::Cardinal(&[workspaces.len() as u32]),
+        );
+        self.api.replace_prop(
+            root,
+            Atom::NetDesktopNames,
+            PropVal::Str(&workspaces.join("\0")),
+        );
+        xconn_shared::update_workarea(&self.api, &self.struts.borrow(), workspaces.len());
+    }
+
+    fn update_known_clients(&self, clients: &[WinId]) {
+        self.api.replace_prop(
+            self.api.root(),
+            Atom::NetClientList,
+            PropVal::Window(clients),
+        );
+        self.api.replace_prop(
+            self.api.root(),
+            Atom::NetClientListStacking,
+            PropVal::Window(clients),
+        );
+    }
+
+    fn set_current_workspace(&self, wix: usize) {
+        self.api.replace_prop(
+            self.api.root(),
+            Atom::NetCurrentDesktop,
+            PropVal::Cardinal(&[wix as u32]),
+        );
+    }
+
+    fn set_root_window_name(&self, root_name: &str) {
+        self.api
+            .replace_prop(self.api.root(), Atom::WmName, PropVal::Str(root_name));
+    }
+
+    fn set_client_workspace(&self, id: WinId, workspace: usize) {
+        self.api.replace_prop(
+            id,
+            Atom::NetWmDesktop,
+            PropVal::Cardinal(&[workspace as u32]),
+        );
+    }
+
+    fn window_should_float(&self, id: WinId, floating_classes: &[&str]) -> bool {
+        if let Ok(s) = self.str_prop(id, Atom::WmClass.as_ref()) {
+            if s.split('\0').any(|c| floating_classes.contains(&c)) {
+                return true;
+            }
+        }
+        if self.transient_for(id).is_some() {
+            return true;
+        }
+        self.window_has_type_in(id, &self.auto_float_types)
+    }
+
+    /// Read `id`'s ICCCM `WM_TRANSIENT_FOR` property, giving the window it is
+    /// a dialog/tool-window for, if any.
+    fn transient_for(&self, id: WinId) -> Option<WinId> {
+        self.api.get_window_prop(id, Atom::WmTransientFor).ok()
+    }
+
+    /// Compute a [Region] that centers `id` over its `WM_TRANSIENT_FOR`
+    /// parent, falling back to the center of `screen` when there is none or it
+    /// lies on no output.
+    fn center_transient_region(&self, id: WinId, screen: &Screen) -> Region {
+        center_transient_region(&self.api, id, screen)
+    }
+
+    fn is_managed_window(&self, id: WinId) -> bool {
+        !self.window_has_type_in(id, &self.dont_manage_types)
+    }
+
+    fn window_geometry(&self, id: WinId) -> Result<Region> {
+        Ok(self.api.window_geometry(id)?)
+    }
+
+    fn warp_cursor(&self, win_id: Option<WinId>, screen: &Screen) {
+        let (x, y, id) = match win_id {
+            Some(id) => {
+                let (_, _, w, h) = match self.window_geometry(id) {
+                    Ok(region) => region.values(),
+                    Err(e) => {
+                        error!("error fetching window details while warping cursor: {}", e);
+                        return;
+                    }
+                };
+                ((w / 2), (h / 2), id)
+            }
+            None => {
+                let (x, y, w, h) = screen.region(true).values();
+                ((x + w / 2), (y + h / 2), self.api.root())
+            }
+        };
+
+        self.api.warp_cursor(id, x as usize, y as usize);
+    }
+
+    fn query_for_active_windows(&self) -> Vec<WinId> {
+        match self.api.current_clients() {
+            Err(_) => Vec::new(),
+            Ok(ids) => ids
+                .iter()
+                .filter(|&id| !self.window_has_type_in(*id, &self.dont_manage_types))
+                .filter(|&id| self.window_state(*id) != WmState::Withdrawn)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Read `id`'s ICCCM `WM_STATE`, defaulting to [WmState::Normal] for
+    /// clients that have never had one set (e.g. newly mapped windows).
+    fn window_state(&self, id: WinId) -> WmState {
+        window_state(&self.api, id)
+    }
+
+    fn str_prop(&self, id: u32, name: &str) -> Result<String> {
+        Ok(self.api.get_str_prop(id, name)?)
+    }
+
+    fn atom_prop(&self, id: u32, name: &str) -> Result<u32> {
+        Ok(self.api.get_atom_prop(id, Atom::from_str(name)?)?)
+    }
+
+    fn intern_atom(&self, atom: &str) -> Result<u32> {
+        Ok(self.api.atom(atom)?)
+    }
+
+    // - Release all of the keybindings we are holding on to
+    // - destroy the check window
+    // - mark ourselves as no longer being the active root window
+    fn cleanup(&self) {
+        self.api.ungrab_keys();
+        self.api.ungrab_mouse_buttons();
+        self.api.destroy_window(self.check_win);
+        self.api.delete_prop(self.api.root(), Atom::NetActiveWindow);
+    }
+}
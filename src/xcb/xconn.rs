@@ -23,13 +23,60 @@ use crate::{
         },
     },
     xcb::{Api, XcbApi},
+    xconn_shared::{
+        self, accepts_input_focus, center_transient_region, clamp_to_size_hints,
+        parse_size_hints, set_window_state, supports_protocol, window_state, HintsApi, SizeHints,
+        Strut, WmState,
+    },
     Result,
 };
 
-use std::{collections::HashMap, str::FromStr};
+use std::{cell::RefCell, collections::HashMap, str::FromStr};
 
 const WM_NAME: &str = "penrose";
 
+impl HintsApi for Api {
+    fn known_atom(&self, atom: Atom) -> u32 {
+        Api::known_atom(self, atom)
+    }
+
+    fn get_atom_list_prop(&self, id: WinId, atom: Atom) -> Result<Vec<u32>> {
+        Ok(Api::get_atom_list_prop(self, id, atom)?)
+    }
+
+    fn get_cardinal_list_prop(&self, id: WinId, atom: Atom) -> Result<Vec<u32>> {
+        Ok(Api::get_cardinal_list_prop(self, id, atom)?)
+    }
+
+    fn get_wm_hints_input(&self, id: WinId) -> Result<bool> {
+        Ok(Api::get_wm_hints_input(self, id)?)
+    }
+
+    fn get_window_prop(&self, id: WinId, atom: Atom) -> Result<WinId> {
+        Ok(Api::get_window_prop(self, id, atom)?)
+    }
+
+    fn root(&self) -> WinId {
+        Api::root(self)
+    }
+
+    fn current_screens(&self) -> Result<Vec<Screen>> {
+        Ok(Api::current_screens(self)?)
+    }
+
+    fn window_geometry(&self, id: WinId) -> Result<Region> {
+        Ok(Api::window_geometry(self, id)?)
+    }
+
+    fn replace_cardinal_prop(&self, id: WinId, atom: Atom, data: &[u32]) {
+        Api::replace_prop(self, id, atom, PropVal::Cardinal(data))
+    }
+
+    fn change_property(&self, id: WinId, name: Atom, type_: u32, format: u8, data: &[u32]) {
+        Api::change_property(self, id, name, type_, format, data)
+    }
+}
+
 /**
  * Handles communication with an X server via the XCB library.
  *
@@ -43,6 +90,8 @@ pub struct XcbConnection {
     check_win: WinId,
     auto_float_types: Vec<u32>,
     dont_manage_types: Vec<u32>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    struts: RefCell<HashMap<WinId, Strut>>,
 }
 
 impl XcbConnection {
@@ -58,6 +107,12 @@ impl XcbConnection {
             .map(|a| api.known_atom(*a))
             .collect();
 
+        // Pre-intern the atoms needed for ICCCM WM_PROTOCOLS support so that the
+        // first kill_client/focus_client call doesn't pay for a round trip.
+        api.known_atom(Atom::WmProtocols);
+        api.known_atom(Atom::WmDeleteWindow);
+        api.known_atom(Atom::WmTakeFocus);
+
         api.set_randr_notify_mask()?;
         let check_win = api.create_window(WinType::CheckWin, Region::new(0, 0, 1, 1), false)?;
 
@@ -66,6 +121,7 @@ impl XcbConnection {
             check_win,
             auto_float_types,
             dont_manage_types,
+            struts: RefCell::new(HashMap::new()),
         })
     }
 
@@ -76,6 +132,21 @@ impl XcbConnection {
         false
     }
 
+    /// Re-read `id`'s strut and update the cache used by [current_outputs][XConn::current_outputs]
+    /// to compute each screen's effective working area. Call whenever a client
+    /// with a strut maps or has its strut property changed; [unmap_window][XConn::unmap_window]
+    /// drops the unmapped client's strut itself.
+    pub fn update_strut(&self, id: WinId) {
+        match xconn_shared::read_strut(&self.api, id) {
+            Some(strut) => {
+                self.struts.borrow_mut().insert(id, strut);
+            }
+            None => {
+                self.struts.borrow_mut().remove(&id);
+            }
+        }
+    }
+
     /// Get a handle on the underlying [XCB Connection][::xcb::Connection] used by [Api]
     /// to communicate with the X server.
     pub fn xcb_connection(&self) -> &xcb::Connection {
@@ -127,7 +198,18 @@ impl XConn for XcbConnection {
 
     fn current_outputs(&self) -> Vec<Screen> {
         match self.api.current_screens() {
-            Ok(screens) => screens,
+            Ok(mut screens) => {
+                let root = xconn_shared::root_bounds(&screens);
+                for screen in screens.iter_mut() {
+                    let effective = xconn_shared::apply_struts(
+                        screen.region(false),
+                        root,
+                        &self.struts.borrow(),
+                    );
+                    screen.set_effective_region(effective);
+                }
+                screens
+            }
             Err(e) => panic!("{}", e),
         }
     }
@@ -136,7 +218,47 @@ impl XConn for XcbConnection {
         self.api.cursor_position()
     }
 
-    fn position_window(&self, id: WinId, reg: Region, border: u32, stack_above: bool) {
+    fn position_window(
+        &self,
+        id: WinId,
+        reg: Region,
+        border: u32,
+        stack_above: bool,
+        floating: bool,
+    ) {
+        let reg = if floating {
+            let reg = match self.size_hints_for(id) {
+                Some(hints) => {
+                    let (x, y, w, h) = reg.values();
+                    let (w, h) = clamp_to_size_hints(w, h, &hints);
+                    Region::new(x, y, w, h)
+                }
+                None => reg,
+            };
+
+            // A transient (window_should_float floats it automatically) is
+            // still only auto-floated, not auto-placed: center it over its
+            // WM_TRANSIENT_FOR parent (or the screen under it) the same way a
+            // dialog box would be positioned by hand.
+            if self.transient_for(id).is_some() {
+                let (x, y, _, _) = reg.values();
+                match self
+                    .api
+                    .current_screens()
+                    .ok()
+                    .as_deref()
+                    .and_then(|screens| xconn_shared::screen_at(screens, x, y))
+                {
+                    Some(screen) => self.center_transient_region(id, screen),
+                    None => reg,
+                }
+            } else {
+                reg
+            }
+        } else {
+            reg
+        };
+
         let mut data = vec![WinConfig::Position(reg), WinConfig::BorderPx(border)];
         if stack_above {
             data.push(WinConfig::StackAbove);
@@ -144,6 +266,14 @@ impl XConn for XcbConnection {
         self.api.configure_window(id, &data)
     }
 
+    /// Read and decode `id`'s ICCCM `WM_NORMAL_HINTS` property, if it has one.
+    fn size_hints_for(&self, id: WinId) -> Option<SizeHints> {
+        self.api
+            .get_cardinal_list_prop(id, Atom::WmNormalHints)
+            .ok()
+            .and_then(|vals| parse_size_hints(&vals))
+    }
+
     fn raise_window(&self, id: WinId) {
         self.api.configure_window(id, &[WinConfig::StackAbove])
     }
@@ -155,10 +285,19 @@ impl XConn for XcbConnection {
 
     fn map_window(&self, id: WinId) {
         self.api.map_window(id);
+        self.update_strut(id);
+        self.set_window_state(id, WmState::Normal);
     }
 
     fn unmap_window(&self, id: WinId) {
         self.api.unmap_window(id);
+        self.struts.borrow_mut().remove(&id);
+        self.set_window_state(id, WmState::Iconic);
+    }
+
+    /// Write `id`'s ICCCM `WM_STATE` property.
+    fn set_window_state(&self, id: WinId, state: WmState) {
+        set_window_state(&self.api, id, state);
     }
 
     fn send_client_event(&self, id: WinId, atom_name: &str) -> Result<()> {
@@ -169,8 +308,37 @@ impl XConn for XcbConnection {
         self.api.focused_client().unwrap_or(0)
     }
 
-    fn focus_client(&self, id: WinId) {
+    /// Focus `id`, additionally sending it a `WM_TAKE_FOCUS` ClientMessage
+    /// (carrying the timestamp of the event that triggered this focus change,
+    /// as ICCCM 4.2.7 forbids `CurrentTime` here) if it wants to manage its
+    /// own input focus.
+    fn focus_client(&self, id: WinId, timestamp: u32) {
         self.api.mark_focused_window(id);
+        if supports_protocol(&self.api, id, Atom::WmTakeFocus)
+            && !accepts_input_focus(&self.api, id)
+        {
+            let data = [self.api.known_atom(Atom::WmTakeFocus), timestamp, 0, 0, 0];
+            self.api
+                .send_client_event_data(id, Atom::WmProtocols, &data);
+        }
+    }
+
+    /// Close `id` via the ICCCM `WM_DELETE_WINDOW` protocol if it is supported,
+    /// otherwise fall back to forcibly killing the client connection.
+    fn kill_client(&self, id: WinId) {
+        if supports_protocol(&self.api, id, Atom::WmDeleteWindow) {
+            let data = [
+                self.api.known_atom(Atom::WmDeleteWindow),
+                xcb::CURRENT_TIME,
+                0,
+                0,
+                0,
+            ];
+            self.api
+                .send_client_event_data(id, Atom::WmProtocols, &data);
+        } else {
+            self.api.kill_client(id);
+        }
     }
 
     fn set_client_border_color(&self, id: WinId, color: u32) {
@@ -238,6 +406,7 @@ impl XConn for XcbConnection {
             Atom::NetDesktopNames,
             PropVal::Str(&workspaces.join("\0")),
         );
+        xconn_shared::update_workarea(&self.api, &self.struts.borrow(), workspaces.len());
     }
 
     fn update_known_clients(&self, clients: &[WinId]) {
@@ -280,9 +449,25 @@ impl XConn for XcbConnection {
                 return true;
             }
         }
+        if self.transient_for(id).is_some() {
+            return true;
+        }
         self.window_has_type_in(id, &self.auto_float_types)
     }
 
+    /// Read `id`'s ICCCM `WM_TRANSIENT_FOR` property, giving the window it is
+    /// a dialog/tool-window for, if any.
+    fn transient_for(&self, id: WinId) -> Option<WinId> {
+        self.api.get_window_prop(id, Atom::WmTransientFor).ok()
+    }
+
+    /// Compute a [Region] that centers `id` over its `WM_TRANSIENT_FOR`
+    /// parent, falling back to the center of `screen` when there is none or it
+    /// lies on no output.
+    fn center_transient_region(&self, id: WinId, screen: &Screen) -> Region {
+        center_transient_region(&self.api, id, screen)
+    }
+
     fn is_managed_window(&self, id: WinId) -> bool {
         !self.window_has_type_in(id, &self.dont_manage_types)
     }
@@ -318,11 +503,18 @@ impl XConn for XcbConnection {
             Ok(ids) => ids
                 .iter()
                 .filter(|&id| !self.window_has_type_in(*id, &self.dont_manage_types))
+                .filter(|&id| self.window_state(*id) != WmState::Withdrawn)
                 .cloned()
                 .collect(),
         }
     }
 
+    /// Read `id`'s ICCCM `WM_STATE`, defaulting to [WmState::Normal] for
+    /// clients that have never had one set (e.g. newly mapped windows).
+    fn window_state(&self, id: WinId) -> WmState {
+        window_state(&self.api, id)
+    }
+
     fn str_prop(&self, id: u32, name: &str) -> Result<String> {
         Ok(self.api.get_str_prop(id, name)?)
     }
@@ -0,0 +1,600 @@
+/*!
+ *  Pure, backend-agnostic logic shared by the `xcb` and `x11rb` [XConn][crate::core::xconnection::XConn]
+ *  implementations.
+ *
+ *  Both backends parse the same ICCCM/EWMH wire formats (`WM_NORMAL_HINTS`,
+ *  `_NET_WM_STRUT_PARTIAL`, `WM_STATE`, ...) into the same neutral types, so
+ *  that logic lives here once instead of being copy-pasted between
+ *  `xcb::xconn` and `x11rb::xconn`. The [HintsApi] trait is the minimal set of
+ *  property-read/write primitives each backend's `Api` needs to provide for
+ *  the free functions below to run against it.
+ */
+use crate::{
+    core::{data_types::Region, screen::Screen, xconnection::Atom},
+    Result,
+};
+
+use std::collections::HashMap;
+
+pub(crate) type WinId = crate::core::data_types::WinId;
+
+/// The property-level primitives that [XcbApi][crate::xcb::XcbApi] and
+/// [X11rbApi][crate::x11rb::X11rbApi] both already provide, abstracted so the
+/// hint/strut/state parsing below can run against either backend.
+pub(crate) trait HintsApi {
+    fn known_atom(&self, atom: Atom) -> u32;
+    fn get_atom_list_prop(&self, id: WinId, atom: Atom) -> Result<Vec<u32>>;
+    fn get_cardinal_list_prop(&self, id: WinId, atom: Atom) -> Result<Vec<u32>>;
+    fn get_wm_hints_input(&self, id: WinId) -> Result<bool>;
+    fn get_window_prop(&self, id: WinId, atom: Atom) -> Result<WinId>;
+    fn root(&self) -> WinId;
+    fn current_screens(&self) -> Result<Vec<Screen>>;
+    fn window_geometry(&self, id: WinId) -> Result<Region>;
+    /// Write a `CARDINAL`-typed property, e.g. `_NET_WORKAREA`.
+    fn replace_cardinal_prop(&self, id: WinId, atom: Atom, data: &[u32]);
+    /// Write a property with an explicit `type_` atom, bypassing `PropVal`
+    /// for the handful of properties (e.g. `WM_STATE`) whose ICCCM-mandated
+    /// type isn't one of `PropVal`'s variants.
+    fn change_property(&self, id: WinId, name: Atom, type_: u32, format: u8, data: &[u32]);
+}
+
+/// Whether the `[start, end)` span of a strut overlaps `[lo, hi)` of the screen
+/// edge it runs alongside. A `(0, 0)` span (as produced by the legacy 4-value
+/// `_NET_WM_STRUT`, which carries no span at all) is treated as covering the
+/// full edge.
+pub(crate) fn overlaps(lo: u32, hi: u32, start: u32, end: u32) -> bool {
+    (start == 0 && end == 0) || (start < hi && end > lo)
+}
+
+// ICCCM `WM_SIZE_HINTS.flags` bits that we care about (see `<X11/Xutil.h>`).
+const P_MIN_SIZE: u32 = 1 << 4;
+const P_MAX_SIZE: u32 = 1 << 5;
+const P_RESIZE_INC: u32 = 1 << 6;
+const P_ASPECT: u32 = 1 << 7;
+const P_BASE_SIZE: u32 = 1 << 8;
+
+/// ICCCM `WM_STATE` values, written to the `WM_STATE` property so that pagers,
+/// taskbars and penrose's own restart logic can distinguish a managed-but-hidden
+/// client from one that has been withdrawn entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WmState {
+    Withdrawn = 0,
+    Normal = 1,
+    Iconic = 3,
+}
+
+impl From<u32> for WmState {
+    fn from(raw: u32) -> Self {
+        match raw {
+            1 => WmState::Normal,
+            3 => WmState::Iconic,
+            _ => WmState::Withdrawn,
+        }
+    }
+}
+
+/// Decoded ICCCM `WM_NORMAL_HINTS` (the `XSizeHints` struct) for a client,
+/// giving the program's preferred sizing constraints for floating placement.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SizeHints {
+    pub min_size: Option<(u32, u32)>,
+    pub max_size: Option<(u32, u32)>,
+    pub resize_inc: Option<(u32, u32)>,
+    pub base_size: Option<(u32, u32)>,
+    pub min_aspect: Option<(u32, u32)>,
+    pub max_aspect: Option<(u32, u32)>,
+}
+
+/// Parse the 18 CARDINALs of a raw `WM_NORMAL_HINTS` property into [SizeHints],
+/// gating each field on its `flags` bit as ICCCM requires.
+pub(crate) fn parse_size_hints(vals: &[u32]) -> Option<SizeHints> {
+    if vals.len() < 18 {
+        return None;
+    }
+
+    let flags = vals[0];
+    let mut hints = SizeHints::default();
+
+    if flags & P_MIN_SIZE != 0 {
+        hints.min_size = Some((vals[5], vals[6]));
+    }
+    if flags & P_MAX_SIZE != 0 {
+        hints.max_size = Some((vals[7], vals[8]));
+    }
+    if flags & P_RESIZE_INC != 0 {
+        hints.resize_inc = Some((vals[9], vals[10]));
+    }
+    if flags & P_ASPECT != 0 {
+        hints.min_aspect = Some((vals[11], vals[12]));
+        hints.max_aspect = Some((vals[13], vals[14]));
+    }
+    if flags & P_BASE_SIZE != 0 {
+        hints.base_size = Some((vals[15], vals[16]));
+    }
+
+    Some(hints)
+}
+
+/// Clamp `(w, h)` into `hints`' min/max bounds, snap to its resize increments
+/// from its base size, and pull it back within its aspect ratio range.
+///
+/// ICCCM defines the aspect ratio over the base-subtracted dimensions
+/// (`(w - base_w) / (h - base_h)`), with `min_size` standing in for
+/// `base_size` when `base_size` is not set, so a terminal
+/// with a cell `base_size` is clamped against its *content* ratio rather than
+/// its window ratio.
+pub(crate) fn clamp_to_size_hints(w: u32, h: u32, hints: &SizeHints) -> (u32, u32) {
+    let (mut w, mut h) = (w, h);
+
+    if let Some((min_w, min_h)) = hints.min_size {
+        w = w.max(min_w);
+        h = h.max(min_h);
+    }
+    if let Some((max_w, max_h)) = hints.max_size {
+        if max_w > 0 {
+            w = w.min(max_w);
+        }
+        if max_h > 0 {
+            h = h.min(max_h);
+        }
+    }
+
+    let base = hints.base_size.or(hints.min_size).unwrap_or((0, 0));
+    if let Some((inc_w, inc_h)) = hints.resize_inc {
+        if inc_w > 0 && w >= base.0 {
+            w = base.0 + ((w - base.0) / inc_w) * inc_w;
+        }
+        if inc_h > 0 && h >= base.1 {
+            h = base.1 + ((h - base.1) / inc_h) * inc_h;
+        }
+    }
+
+    if let (Some((min_n, min_d)), Some((max_n, max_d))) = (hints.min_aspect, hints.max_aspect) {
+        let aspect_base = hints.base_size.or(hints.min_size).unwrap_or((0, 0));
+        let aw = w.saturating_sub(aspect_base.0);
+        let ah = h.saturating_sub(aspect_base.1);
+
+        if min_d > 0 && max_d > 0 && ah > 0 {
+            let ratio = aw as f64 / ah as f64;
+            let min_ratio = min_n as f64 / min_d as f64;
+            let max_ratio = max_n as f64 / max_d as f64;
+            if ratio < min_ratio {
+                h = aspect_base.1 + (aw as f64 / min_ratio).round() as u32;
+            } else if ratio > max_ratio {
+                h = aspect_base.1 + (aw as f64 / max_ratio).round() as u32;
+            }
+        }
+    }
+
+    (w, h)
+}
+
+/// The reserved screen-edge thickness requested by a client via
+/// `_NET_WM_STRUT_PARTIAL` (or the older, coarser `_NET_WM_STRUT`).
+///
+/// The four `_start`/`_end` pairs bound the span of each reserved edge along the
+/// opposite axis so that a strut only eats into the screen(s) it actually runs
+/// alongside: see the [EWMH spec](https://specifications.freedesktop.org/wm-spec/wm-spec-1.3.html#NETWMSTRUTPARTIAL).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Strut {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+    pub left_start_y: u32,
+    pub left_end_y: u32,
+    pub right_start_y: u32,
+    pub right_end_y: u32,
+    pub top_start_x: u32,
+    pub top_end_x: u32,
+    pub bottom_start_x: u32,
+    pub bottom_end_x: u32,
+}
+
+/// Whether `id` advertises `protocol` in its ICCCM `WM_PROTOCOLS` property.
+pub(crate) fn supports_protocol<A: HintsApi>(api: &A, id: WinId, protocol: Atom) -> bool {
+    let target = api.known_atom(protocol);
+    api.get_atom_list_prop(id, Atom::WmProtocols)
+        .map(|atoms| atoms.contains(&target))
+        .unwrap_or(false)
+}
+
+/// Whether `id`'s `WM_HINTS` advertise that it accepts input focus from the
+/// window manager (the `input` flag is set, or `WM_HINTS` is absent). `false`
+/// means the client wants to manage its own input focus.
+pub(crate) fn accepts_input_focus<A: HintsApi>(api: &A, id: WinId) -> bool {
+    api.get_wm_hints_input(id).unwrap_or(true)
+}
+
+/// Read `id`'s reserved screen-edge thickness, preferring the 12-value
+/// `_NET_WM_STRUT_PARTIAL` and falling back to the older 4-value
+/// `_NET_WM_STRUT` when partial struts aren't set.
+pub(crate) fn read_strut<A: HintsApi>(api: &A, id: WinId) -> Option<Strut> {
+    if let Ok(vals) = api.get_cardinal_list_prop(id, Atom::NetWmStrutPartial) {
+        if vals.len() == 12 {
+            return Some(Strut {
+                left: vals[0],
+                right: vals[1],
+                top: vals[2],
+                bottom: vals[3],
+                left_start_y: vals[4],
+                left_end_y: vals[5],
+                right_start_y: vals[6],
+                right_end_y: vals[7],
+                top_start_x: vals[8],
+                top_end_x: vals[9],
+                bottom_start_x: vals[10],
+                bottom_end_x: vals[11],
+            });
+        }
+    }
+
+    if let Ok(vals) = api.get_cardinal_list_prop(id, Atom::NetWmStrut) {
+        if vals.len() == 4 {
+            return Some(Strut {
+                left: vals[0],
+                right: vals[1],
+                top: vals[2],
+                bottom: vals[3],
+                ..Default::default()
+            });
+        }
+    }
+
+    None
+}
+
+/// The bounding box enclosing every output, in the same absolute (root)
+/// coordinate space `Strut` thicknesses are measured against.
+pub(crate) fn root_bounds(screens: &[Screen]) -> Region {
+    if screens.is_empty() {
+        return Region::new(0, 0, 0, 0);
+    }
+
+    let mut min_x = u32::MAX;
+    let mut min_y = u32::MAX;
+    let mut max_x = 0;
+    let mut max_y = 0;
+
+    for screen in screens {
+        let (x, y, w, h) = screen.region(false).values();
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x + w);
+        max_y = max_y.max(y + h);
+    }
+
+    Region::new(min_x, min_y, max_x - min_x, max_y - min_y)
+}
+
+/// Shrink `region` by the accumulated reserved edge thickness of every known
+/// strut that runs alongside it.
+///
+/// `_NET_WM_STRUT(_PARTIAL)` thicknesses are measured from the edges of the
+/// root window, not of the individual output `region` sits within, so a
+/// strut is only relevant to `region` if the slice of the root it actually
+/// reserves overlaps `region`'s own bounds along that axis: a panel docked
+/// to the left edge of a left-hand monitor must not also eat into a monitor
+/// to its right.
+pub(crate) fn apply_struts(region: Region, root: Region, struts: &HashMap<WinId, Strut>) -> Region {
+    let (mut x, mut y, mut w, mut h) = region.values();
+    let (sx, sy, sw, sh) = (x, y, w, h);
+    let (rx, ry, rw, rh) = root.values();
+
+    for strut in struts.values() {
+        if strut.left > 0 && overlaps(sy, sy + sh, strut.left_start_y, strut.left_end_y) {
+            let edge = rx + strut.left;
+            if edge > x {
+                let cut = (edge - x).min(w);
+                x += cut;
+                w -= cut;
+            }
+        }
+        if strut.right > 0 && overlaps(sy, sy + sh, strut.right_start_y, strut.right_end_y) {
+            let edge = (rx + rw).saturating_sub(strut.right);
+            if edge < x + w {
+                w -= ((x + w) - edge).min(w);
+            }
+        }
+        if strut.top > 0 && overlaps(sx, sx + sw, strut.top_start_x, strut.top_end_x) {
+            let edge = ry + strut.top;
+            if edge > y {
+                let cut = (edge - y).min(h);
+                y += cut;
+                h -= cut;
+            }
+        }
+        if strut.bottom > 0 && overlaps(sx, sx + sw, strut.bottom_start_x, strut.bottom_end_x) {
+            let edge = (ry + rh).saturating_sub(strut.bottom);
+            if edge < y + h {
+                h -= ((y + h) - edge).min(h);
+            }
+        }
+    }
+
+    Region::new(x, y, w, h)
+}
+
+/// The bounding box of every output's effective (strut-shrunk) working area,
+/// for publishing as `_NET_WORKAREA`. A single-output bounding box is just
+/// that output's effective region; on a multi-monitor setup this is the box
+/// enclosing all of them, so space reserved on one output is not reflected
+/// where another output's working area extends past it.
+pub(crate) fn workarea_region<A: HintsApi>(api: &A, struts: &HashMap<WinId, Strut>) -> Region {
+    let screens = api.current_screens().unwrap_or_default();
+    if screens.is_empty() {
+        return Region::new(0, 0, 0, 0);
+    }
+
+    let root = root_bounds(&screens);
+    let mut min_x = u32::MAX;
+    let mut min_y = u32::MAX;
+    let mut max_x = 0;
+    let mut max_y = 0;
+
+    for screen in &screens {
+        let (x, y, w, h) = apply_struts(screen.region(false), root, struts).values();
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x + w);
+        max_y = max_y.max(y + h);
+    }
+
+    Region::new(min_x, min_y, max_x - min_x, max_y - min_y)
+}
+
+/// Publish the aggregate effective working area of all outputs as
+/// `_NET_WORKAREA`, repeated once per desktop as EWMH requires.
+pub(crate) fn update_workarea<A: HintsApi>(api: &A, struts: &HashMap<WinId, Strut>, n_desktops: usize) {
+    let (x, y, w, h) = workarea_region(api, struts).values();
+    let per_desktop = [x, y, w, h];
+    let data: Vec<u32> = per_desktop
+        .iter()
+        .cloned()
+        .cycle()
+        .take(4 * n_desktops)
+        .collect();
+
+    api.replace_cardinal_prop(api.root(), Atom::NetWorkarea, &data);
+}
+
+/// Read `id`'s ICCCM `WM_STATE`, defaulting to [WmState::Normal] for clients
+/// that have never had one set (e.g. newly mapped windows).
+pub(crate) fn window_state<A: HintsApi>(api: &A, id: WinId) -> WmState {
+    match api.get_cardinal_list_prop(id, Atom::WmState) {
+        Ok(vals) if !vals.is_empty() => WmState::from(vals[0]),
+        _ => WmState::Normal,
+    }
+}
+
+/// Write `id`'s ICCCM `WM_STATE` property with its ICCCM-mandated type: the
+/// `WM_STATE` atom itself, not `CARDINAL`.
+pub(crate) fn set_window_state<A: HintsApi>(api: &A, id: WinId, state: WmState) {
+    let wm_state_type = api.known_atom(Atom::WmState);
+    api.change_property(id, Atom::WmState, wm_state_type, 32, &[state as u32, 0]);
+}
+
+/// The screen whose region contains the point `(x, y)`, falling back to the
+/// first screen when it falls within none of them (e.g. a window that hasn't
+/// been placed anywhere yet).
+pub(crate) fn screen_at(screens: &[Screen], x: u32, y: u32) -> Option<&Screen> {
+    screens
+        .iter()
+        .find(|s| {
+            let (sx, sy, sw, sh) = s.region(false).values();
+            (sx..sx + sw).contains(&x) && (sy..sy + sh).contains(&y)
+        })
+        .or_else(|| screens.first())
+}
+
+/// Compute a [Region] that centers a floating `id` over the geometry of its
+/// `WM_TRANSIENT_FOR` parent, falling back to the center of `screen` when the
+/// parent is unknown or its center lies on no output. Whether the parent is
+/// mapped is not checked.
+pub(crate) fn center_transient_region<A: HintsApi>(api: &A, id: WinId, screen: &Screen) -> Region {
+    let (_, _, w, h) = api.window_geometry(id).map(|r| r.values()).unwrap_or((0, 0, 0, 0));
+
+    let parent_region = api
+        .get_window_prop(id, Atom::WmTransientFor)
+        .ok()
+        .and_then(|parent| api.window_geometry(parent).ok())
+        .filter(|region| {
+            let (px, py, pw, ph) = region.values();
+            let (cx, cy) = (px + pw / 2, py + ph / 2);
+            api.current_screens().unwrap_or_default().iter().any(|s| {
+                let (sx, sy, sw, sh) = s.region(false).values();
+                (sx..sx + sw).contains(&cx) && (sy..sy + sh).contains(&cy)
+            })
+        });
+
+    let (cx, cy) = match parent_region {
+        Some(region) => {
+            let (px, py, pw, ph) = region.values();
+            (px + pw / 2, py + ph / 2)
+        }
+        None => {
+            let (sx, sy, sw, sh) = screen.region(true).values();
+            (sx + sw / 2, sy + sh / 2)
+        }
+    };
+
+    Region::new(cx.saturating_sub(w / 2), cy.saturating_sub(h / 2), w, h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strut(left: u32, right: u32, top: u32, bottom: u32) -> Strut {
+        Strut {
+            left,
+            right,
+            top,
+            bottom,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overlaps_treats_zero_zero_as_full_span() {
+        assert!(overlaps(100, 200, 0, 0));
+    }
+
+    #[test]
+    fn overlaps_detects_disjoint_spans() {
+        assert!(!overlaps(100, 200, 0, 50));
+        assert!(!overlaps(100, 200, 200, 250));
+    }
+
+    #[test]
+    fn overlaps_detects_intersecting_spans() {
+        assert!(overlaps(100, 200, 150, 160));
+        assert!(overlaps(100, 200, 50, 150));
+    }
+
+    #[test]
+    fn wm_state_from_maps_known_values_and_defaults_to_withdrawn() {
+        assert_eq!(WmState::from(1), WmState::Normal);
+        assert_eq!(WmState::from(3), WmState::Iconic);
+        assert_eq!(WmState::from(0), WmState::Withdrawn);
+        assert_eq!(WmState::from(42), WmState::Withdrawn);
+    }
+
+    #[test]
+    fn parse_size_hints_rejects_short_property() {
+        assert_eq!(parse_size_hints(&[0; 10]), None);
+    }
+
+    #[test]
+    fn parse_size_hints_only_sets_flagged_fields() {
+        let mut vals = [0u32; 18];
+        vals[0] = 1 << 4; // PMinSize
+        vals[5] = 40;
+        vals[6] = 20;
+
+        let hints = parse_size_hints(&vals).unwrap();
+        assert_eq!(hints.min_size, Some((40, 20)));
+        assert_eq!(hints.max_size, None);
+        assert_eq!(hints.resize_inc, None);
+        assert_eq!(hints.base_size, None);
+    }
+
+    #[test]
+    fn parse_size_hints_decodes_every_field() {
+        let mut vals = [0u32; 18];
+        vals[0] = (1 << 4) | (1 << 5) | (1 << 6) | (1 << 7) | (1 << 8);
+        vals[5] = 10; // min w
+        vals[6] = 10; // min h
+        vals[7] = 100; // max w
+        vals[8] = 100; // max h
+        vals[9] = 5; // inc w
+        vals[10] = 5; // inc h
+        vals[11] = 4; // min aspect num
+        vals[12] = 3; // min aspect den
+        vals[13] = 16; // max aspect num
+        vals[14] = 9; // max aspect den
+        vals[15] = 2; // base w
+        vals[16] = 2; // base h
+
+        let hints = parse_size_hints(&vals).unwrap();
+        assert_eq!(hints.min_size, Some((10, 10)));
+        assert_eq!(hints.max_size, Some((100, 100)));
+        assert_eq!(hints.resize_inc, Some((5, 5)));
+        assert_eq!(hints.min_aspect, Some((4, 3)));
+        assert_eq!(hints.max_aspect, Some((16, 9)));
+        assert_eq!(hints.base_size, Some((2, 2)));
+    }
+
+    #[test]
+    fn clamp_to_size_hints_enforces_min_and_max() {
+        let hints = SizeHints {
+            min_size: Some((50, 50)),
+            max_size: Some((200, 200)),
+            ..Default::default()
+        };
+
+        assert_eq!(clamp_to_size_hints(10, 10, &hints), (50, 50));
+        assert_eq!(clamp_to_size_hints(500, 500, &hints), (200, 200));
+        assert_eq!(clamp_to_size_hints(100, 100, &hints), (100, 100));
+    }
+
+    #[test]
+    fn clamp_to_size_hints_snaps_to_increments_from_base() {
+        let hints = SizeHints {
+            base_size: Some((10, 10)),
+            resize_inc: Some((8, 16)),
+            ..Default::default()
+        };
+
+        // 10 + 3*8 = 34, 10 + 2*16 = 42
+        assert_eq!(clamp_to_size_hints(37, 45, &hints), (34, 42));
+    }
+
+    #[test]
+    fn clamp_to_size_hints_uses_base_subtracted_aspect_ratio() {
+        // A terminal reporting a 2px base (chrome) should be clamped against
+        // its *content* ratio, not the raw window ratio.
+        let hints = SizeHints {
+            base_size: Some((2, 2)),
+            min_aspect: Some((1, 1)),
+            max_aspect: Some((1, 1)),
+            ..Default::default()
+        };
+
+        // Content is 100x50 (ratio 2:1) plus a 2x2 base -> should clamp content
+        // height up to match content width, then re-add the base.
+        let (w, h) = clamp_to_size_hints(102, 52, &hints);
+        assert_eq!(w, 102);
+        assert_eq!(h, 102);
+    }
+
+    #[test]
+    fn apply_struts_shrinks_region_for_overlapping_struts() {
+        let region = Region::new(0, 0, 1000, 800);
+        let mut struts = HashMap::new();
+        struts.insert(1, strut(0, 0, 20, 0)); // top bar, full width
+        struts.insert(2, strut(0, 0, 0, 30)); // bottom bar, full width
+
+        let effective = apply_struts(region, region, &struts);
+        assert_eq!(effective.values(), (0, 20, 1000, 750));
+    }
+
+    #[test]
+    fn apply_struts_ignores_struts_outside_their_span() {
+        let region = Region::new(0, 0, 1000, 800);
+        let mut struts = HashMap::new();
+        // A left-edge strut whose span only covers y in [900, 1000), i.e. a
+        // second monitor below this one.
+        let mut s = strut(50, 0, 0, 0);
+        s.left_start_y = 900;
+        s.left_end_y = 1000;
+        struts.insert(1, s);
+
+        assert_eq!(apply_struts(region, region, &struts).values(), (0, 0, 1000, 800));
+    }
+
+    #[test]
+    fn apply_struts_only_shrinks_the_monitor_the_strut_is_docked_to() {
+        // Two side-by-side monitors forming a single root: [0,1000) and
+        // [1000,2000). A panel docked to the *root's* left edge must only
+        // shrink the first monitor, not the second.
+        let root = Region::new(0, 0, 2000, 800);
+        let left_monitor = Region::new(0, 0, 1000, 800);
+        let right_monitor = Region::new(1000, 0, 1000, 800);
+
+        let mut struts = HashMap::new();
+        struts.insert(1, strut(50, 0, 0, 0)); // left-edge panel, full height
+
+        assert_eq!(
+            apply_struts(left_monitor, root, &struts).values(),
+            (50, 0, 950, 800)
+        );
+        assert_eq!(
+            apply_struts(right_monitor, root, &struts).values(),
+            (1000, 0, 1000, 800)
+        );
+    }
+}